@@ -1,54 +1,119 @@
+use chrono::NaiveDateTime;
 use console::style;
+use regex::Regex;
+use rusqlite::backup::Backup;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
 use rusqlite::{Connection, Result};
 
-// Define properties of a todo entry 
-#[derive(Debug)]
+pub mod server;
+
+// Format due_at/completed_at/date_added timestamps are stored and parsed in
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// Upper bound on a search pattern's length, the way an open server would guard user input
+const MAX_SEARCH_PATTERN_LEN: usize = 200;
+
+// Define properties of a todo entry
+#[derive(Debug, Serialize, Deserialize)]
 // Use String for date_added and unsigned integer for is_done to match the available SQLite datatypes
+// completed_at is nullable: it is only ever set while is_done is 1
+// due_at is nullable: tasks added without --due have no due date
 pub struct Task {
     pub id: i32,
     pub name: String,
     pub date_added: String,
     pub is_done: u8,
+    pub completed_at: Option<String>,
+    pub due_at: Option<String>,
+}
+
+// Which subset of tasks Task::list should return
+// Mirrors the `list --finished`/`list --pending` CLI filters
+#[derive(Debug, PartialEq)]
+pub enum TaskFilter {
+    All,
+    Pending,
+    Finished,
+}
+
+// How Task::list should order the rows it returns
+#[derive(Debug, PartialEq)]
+pub enum SortBy {
+    Id,
+    Status,
+    // Soonest due date first; tasks with no due date sort last
+    Due,
 }
 
 // Use impl to define methods for the Task struct
 impl Task {
     // Constructor for a new Task instance
-    pub fn new(id: i32, name: String, date_added: String, is_done: u8) -> Task {
-        Task { 
-            id, 
-            name, 
-            date_added, 
-            is_done 
+    pub fn new(
+        id: i32,
+        name: String,
+        date_added: String,
+        is_done: u8,
+        completed_at: Option<String>,
+        due_at: Option<String>,
+    ) -> Task {
+        Task {
+            id,
+            name,
+            date_added,
+            is_done,
+            completed_at,
+            due_at,
         }
     }
 
-    // Add a new Task to the database
-    pub fn add(conn: &Connection, name: &str) -> Result<()> {
+    // Add a new Task to the database, with an optional natural-language due date
+    // ("next friday", "tomorrow", ...) that gets parsed into an absolute timestamp
+    pub fn add(conn: &Connection, name: &str, due: Option<&str>) -> Result<()> {
+        let due_at: Option<String> = match due {
+            Some(text) => {
+                // A due date typed by a human is entirely plausible to get wrong - print a clean
+                // error and skip the insert rather than panicking the whole process
+                match chrono_english::parse_date_string(text, chrono::Local::now(), chrono_english::Dialect::Us) {
+                    Ok(parsed) => Some(parsed.format(TIMESTAMP_FORMAT).to_string()),
+                    Err(_) => {
+                        println!("Could not understand due date: {:?}", text);
+                        return Ok(());
+                    }
+                }
+            }
+            None => None,
+        };
         // Insert a new row into the tasks table
         conn.execute(
-            "INSERT INTO tasks (name) VALUES (?)",
+            "INSERT INTO tasks (name, due_at) VALUES (?, ?)",
             // The ? placeholder is used to avoid SQL injection attacks
             // The value of name will be inserted into the query in place of the ?
-            // The values must be passed as a reference & slice
-            &[name],
+            rusqlite::params![name, due_at],
         )?;
         Ok(())
     }
 
-    // List all tasks in the database
-    pub fn list(conn: &Connection, sort_by_status: bool) -> Result<Vec<Task>> {
-        // Set the sql query to sort by status if sort_by_status is true or by id if it is false
-        let sql = if sort_by_status {
-            "SELECT * FROM tasks ORDER BY is_done, id"
-        } else {
-            "SELECT * FROM tasks ORDER BY id"
+    // List tasks in the database, optionally restricted to a TaskFilter subset
+    pub fn list(conn: &Connection, filter: TaskFilter, sort_by: SortBy) -> Result<Vec<Task>> {
+        // Narrow the rows to pending/finished tasks when asked, leaving the ORDER BY clause alone
+        let where_clause = match filter {
+            TaskFilter::All => "",
+            TaskFilter::Pending => "WHERE is_done = 0 ",
+            TaskFilter::Finished => "WHERE is_done = 1 ",
+        };
+        let order_clause = match sort_by {
+            SortBy::Id => "ORDER BY id",
+            SortBy::Status => "ORDER BY is_done, id",
+            // due_at IS NULL sorts false (0) before true (1), so dateless tasks end up last
+            SortBy::Due => "ORDER BY due_at IS NULL, due_at",
         };
+        let sql = format!("SELECT * FROM tasks {}{}", where_clause, order_clause);
         // Takes a SQL query and prepares it for execution
         // stmt is a prepared statement - a precompiled SQL statement that can be executed multiple times with different parameters
-        let mut stmt = conn.prepare(sql)?;
+        let mut stmt = conn.prepare(&sql)?;
         // query_map executes the SQL query associated with the prepared statement
         // query_map returns an iterator over the rows returned by the query
         // query_map takes a closure that will be called for each row returned by the query
@@ -62,6 +127,8 @@ impl Task {
                 row.get(1)?,
                 row.get(2)?,
                 row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
             ))
         })?;
         // Collect the results of the query_map iterator into a Vec<Task>
@@ -82,23 +149,51 @@ impl Task {
             } else {
                 style("PENDING").red()
             };
+            // show when a DONE task was completed; PENDING tasks have no completed_at yet
+            let completed_at = task.completed_at.unwrap_or_else(|| "".to_string());
+            // style a pending task's due date by how close it is: overdue in bold red,
+            // due today in yellow, anything further out in the normal dim style
+            let due_at = match &task.due_at {
+                Some(raw) => {
+                    let styled = match NaiveDateTime::parse_from_str(raw, TIMESTAMP_FORMAT) {
+                        Ok(due) if task.is_done == 0 && due < chrono::Local::now().naive_local() => {
+                            style(raw.clone()).red().bold()
+                        }
+                        Ok(due)
+                            if task.is_done == 0
+                                && due.date() == chrono::Local::now().naive_local().date() =>
+                        {
+                            style(raw.clone()).yellow()
+                        }
+                        _ => style(raw.clone()).dim(),
+                    };
+                    styled.to_string()
+                }
+                None => "".to_string(),
+            };
             // expected that the task id does not exceed 4 characters
             // > aligns the text to the right, < aligns the text to the left
             println!(
-                "{:>4} | {:<44} | {:<8} {}",
+                "{:>4} | {:<44} | {:<8} {} {} {}",
                 style(task.id).cyan().bright(),
                 style(truncate(&task.name, 44)).bright(),
                 status,
                 style(task.date_added).dim(),
+                style(completed_at).dim(),
+                due_at,
             );
         }
         Ok(())
     }
 
-    // Toggle the status of a task
+    // Toggle the status of a task, recording or clearing completed_at to match
     pub fn toggle(conn: &Connection, id: i32) -> Result<()> {
-        // Prepare a statement to update the is_done column of a specific task
-        let sql = "UPDATE tasks SET is_done = 1 - is_done WHERE id = ?";
+        // When a task flips to done (1 - is_done = 1, i.e. is_done was 0), stamp completed_at
+        // When it flips back to pending, clear completed_at so it doesn't lie about being finished
+        let sql = "UPDATE tasks SET
+            completed_at = CASE WHEN is_done = 0 THEN current_timestamp ELSE NULL END,
+            is_done = 1 - is_done
+            WHERE id = ?";
         let rows_affected = conn.execute(sql, [id])?;
         // If no rows were affected, print the task with the given id was not found
         // Otherwise, print that the task was toggled
@@ -131,6 +226,96 @@ impl Task {
         }
         Ok(())
     }
+
+    // Write every task to a pretty-printed JSON array at the given path
+    pub fn export(conn: &Connection, path: &str) -> Result<()> {
+        let tasks = Task::list(conn, TaskFilter::All, SortBy::Id)?;
+        // Pretty-printed so the export is diff-friendly when checked into version control
+        let json = serde_json::to_string_pretty(&tasks).expect("Failed to serialize tasks to JSON");
+        // A bad directory, missing permissions, or a full disk are all plausible for a
+        // user-supplied export path - report them cleanly instead of panicking
+        if let Err(e) = fs::write(path, json) {
+            println!("Could not write export file {}: {}", path, e);
+            std::process::exit(1);
+        }
+        println!("Exported {} task(s) to {}", tasks.len(), path);
+        Ok(())
+    }
+
+    // Read a JSON array of tasks from the given path and insert them into the database
+    pub fn import(conn: &Connection, path: &str) -> Result<()> {
+        // A missing file or malformed JSON are plausible user mistakes for a backup/restore
+        // feature - report them cleanly instead of panicking with a backtrace
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) => {
+                println!("Could not read import file {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        let tasks: Vec<Task> = match serde_json::from_str(&json) {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                println!("Could not parse {} as JSON: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        // Reassign ids rather than preserving the exported ones, so importing never collides
+        // with tasks already in the database - AUTOINCREMENT picks the next free id for us
+        for task in &tasks {
+            conn.execute(
+                "INSERT INTO tasks (name, date_added, is_done, completed_at, due_at) VALUES (?, ?, ?, ?, ?)",
+                rusqlite::params![task.name, task.date_added, task.is_done, task.completed_at, task.due_at],
+            )?;
+        }
+        println!("Imported {} task(s) from {}", tasks.len(), path);
+        Ok(())
+    }
+
+    // Find tasks whose name matches `pattern`, either a SQL LIKE substring search (the default)
+    // or, with `use_regex` set, a regex matched in Rust over the fetched rows
+    pub fn search(conn: &Connection, pattern: &str, use_regex: bool) -> Result<Vec<Task>> {
+        // Validate/limit the pattern the way an open server would, rather than trusting it blindly
+        if pattern.is_empty() || pattern.chars().count() > MAX_SEARCH_PATTERN_LEN {
+            println!(
+                "Search pattern must be between 1 and {} characters.",
+                MAX_SEARCH_PATTERN_LEN
+            );
+            return Ok(Vec::new());
+        }
+
+        if use_regex {
+            // Reject a malformed regex with a clean error instead of panicking
+            let re = match Regex::new(pattern) {
+                Ok(re) => re,
+                Err(e) => {
+                    println!("Invalid regex pattern: {}", e);
+                    return Ok(Vec::new());
+                }
+            };
+            let tasks = Task::list(conn, TaskFilter::All, SortBy::Id)?;
+            Ok(tasks.into_iter().filter(|task| re.is_match(&task.name)).collect())
+        } else {
+            let sql = "SELECT * FROM tasks WHERE name LIKE ? ORDER BY id";
+            let mut stmt = conn.prepare(sql)?;
+            let like_pattern = format!("%{}%", pattern);
+            let task_iter = stmt.query_map([like_pattern], |row| {
+                Ok(Task::new(
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            })?;
+            let mut tasks = Vec::new();
+            for task in task_iter {
+                tasks.push(task?);
+            }
+            Ok(tasks)
+        }
+    }
 }
 
 // Truncate a str and adds an ellipsis if needed
@@ -153,13 +338,13 @@ pub fn help() -> Result<()> {
     // r#"..."#; used to create a raw string literal
     // Maintains formatting, indentation, and line breaks
     let help_text = r#"
-        - add [TASK]
-            Adds new task(s)
-            Example: todo add "Build a tree"
+        - add [TASK] [--due WHEN]
+            Adds a new task, optionally with a natural-language due date
+            Example: todo add "Pay rent" --due "next friday"
 
-        - list
-            Lists all tasks
-            Example: todo list
+        - list [--finished|--pending|--due]
+            Lists all tasks, only finished/pending tasks, or sorted by soonest due date
+            Example: todo list --finished
 
         - toggle [ID]
             Toggles the status of a task (Done/Pending)
@@ -174,6 +359,30 @@ pub fn help() -> Result<()> {
 
         - reset
             Deletes all tasks
+
+        - export [FILE]
+            Writes all tasks to a JSON file (defaults to tasks_export.json)
+            Example: todo export backup.json
+
+        - import [FILE]
+            Reads tasks from a JSON file and adds them (defaults to tasks_export.json)
+            Example: todo import backup.json
+
+        - serve [--port N]
+            Starts an HTTP API exposing the task store (defaults to port 8080)
+            Example: todo serve --port 3000
+
+        - backup [DEST]
+            Safely copies the live database to DEST (defaults to tasks_backup.sqlite)
+            Example: todo backup tasks_backup.sqlite
+
+        - restore [SRC]
+            Safely restores the database from SRC (defaults to tasks_backup.sqlite)
+            Example: todo restore tasks_backup.sqlite
+
+        - search [PATTERN] [--regex]
+            Finds tasks by substring, or by regex with --regex
+            Example: todo search "rent" --regex
     "#;
 
     println!("{}", style(help_title).magenta().bright());
@@ -203,23 +412,59 @@ pub fn verify_db_path(db_folder: &str) -> Result<()> {
     Ok(())
 }
 
-// Creates tables if they do not exist
-pub fn verify_db(conn: &Connection) -> Result<()> {
-    // Create the table if it does not exist
-    // AUTOINCREMENT will set the id, or primary key, to be 1 if it is the first row inserted
-    // Otherwise, it will increment the id of the last inserted row by 1
-    // The is_done column will be set to 0 by default, as the task is not done when it is added
-    // The date_added column will be set to the current timestamp by default
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS tasks (
+// A single schema migration: the SQL to run and the user_version it brings the DB to
+// Migrations are applied in order, so `version` should increase by 1 for each new entry
+struct Migration {
+    version: i32,
+    sql: &'static str,
+}
+
+// Ordered list of every migration the schema has ever needed
+// To evolve the schema (e.g. adding a column), append a new Migration here with version + 1 -
+// never edit an already-released migration, since that would skip it for existing databases
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        // AUTOINCREMENT will set the id, or primary key, to be 1 if it is the first row inserted
+        // Otherwise, it will increment the id of the last inserted row by 1
+        // The is_done column will be set to 0 by default, as the task is not done when it is added
+        // The date_added column will be set to the current timestamp by default
+        sql: "CREATE TABLE IF NOT EXISTS tasks (
             id INTEGER NOT NULL,
             name TEXT NOT NULL,
             date_added TEXT NOT NULL DEFAULT current_timestamp,
             is_done INTEGER NOT NULL DEFAULT 0,
             PRIMARY KEY (id AUTOINCREMENT)
         )",
-        [], // no parameters for this query
-    )?;
+    },
+    Migration {
+        version: 2,
+        // Nullable: only populated once a task is toggled to done, cleared if toggled back
+        sql: "ALTER TABLE tasks ADD COLUMN completed_at TEXT",
+    },
+    Migration {
+        version: 3,
+        // Nullable: only populated when a task is added with --due
+        sql: "ALTER TABLE tasks ADD COLUMN due_at TEXT",
+    },
+];
+
+// Creates tables if they do not exist, and brings an older database up to the latest schema
+pub fn verify_db(conn: &Connection) -> Result<()> {
+    // PRAGMA user_version is SQLite's built-in integer for tracking schema version
+    // A brand new database reports 0, so every migration below will run in order
+    let current: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for migration in MIGRATIONS {
+        if migration.version > current {
+            // Run the migration's SQL and the version bump together so a failure partway
+            // through a migration can't leave the DB reporting a version it hasn't reached
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(migration.sql, [])?;
+            tx.pragma_update(None, "user_version", migration.version)?;
+            tx.commit()?;
+        }
+    }
 
     Ok(())
 }
@@ -238,4 +483,25 @@ pub fn get_connection() -> Result<Connection> {
     // Verify that the database contains the expected table
     verify_db(&conn)?;
     Ok(conn)
+}
+
+// Copies the live database to `dest` using SQLite's online backup API, in pages, so it produces
+// a consistent snapshot even while the database is being written to - unlike copying the raw file
+pub fn backup_db(conn: &Connection, dest: &str) -> Result<()> {
+    let mut dst_conn = Connection::open(dest)?;
+    let backup = Backup::new(conn, &mut dst_conn)?;
+    // Copy 5 pages at a time, pausing briefly between chunks so a long backup doesn't
+    // starve other connections of access to the database
+    backup.run_to_completion(5, Duration::from_millis(250), None)?;
+    println!("Backed up database to {}", dest);
+    Ok(())
+}
+
+// Restores `src` into the active connection using the same online backup API, in reverse
+pub fn restore_db(conn: &mut Connection, src: &str) -> Result<()> {
+    let src_conn = Connection::open(src)?;
+    let backup = Backup::new(&src_conn, conn)?;
+    backup.run_to_completion(5, Duration::from_millis(250), None)?;
+    println!("Restored database from {}", src);
+    Ok(())
 }
\ No newline at end of file
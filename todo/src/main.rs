@@ -14,7 +14,7 @@ fn main() -> Result<()> {
     
     // Get a connection to the DB
     // If it fails it returns the error, but if it works conn will be assigned properly
-    let conn = get_connection()?;
+    let mut conn = get_connection()?;
     
     // If no arguments have been passed in (args only contains the program path)
     if args.len() == 1 {
@@ -44,26 +44,49 @@ fn main() -> Result<()> {
         // If the command is "add" and the suffix is empty, print help and exit
         // Otherwise, add the task to the DB
         "add" => {
+            let rest = &args[2..];
             // If no task name is provided, print help and exit
-            if suffix.as_str().is_empty() {
+            if rest.is_empty() {
                 help()?;
                 std::process::exit(1);
             }
-            // Otherwise, add the task to the DB
-            else {
-                Task::add(&conn, suffix.as_str())?;
+            // Split the arguments on "--due" into the task name and an optional natural-language
+            // due date, e.g. `todo add "Pay rent" --due "next friday"`
+            let due_index = rest.iter().position(|arg| arg == "--due");
+            let (name_parts, due) = match due_index {
+                Some(i) => (&rest[..i], Some(rest[i + 1..].join(" "))),
+                None => (rest, None),
+            };
+            let name = name_parts.join(" ");
+            if name.is_empty() {
+                help()?;
+                std::process::exit(1);
             }
+            Task::add(&conn, &name, due.as_deref())?;
             Ok(())
         }
         "list" => {
+            // Pick which subset of tasks to show based on the optional --finished/--pending flag
+            let (filter, label) = match suffix.as_str() {
+                "--finished" => (TaskFilter::Finished, "finished tasks"),
+                "--pending" => (TaskFilter::Pending, "pending tasks"),
+                _ => (TaskFilter::All, "tasks"),
+            };
+            // Sort by soonest due date when --due is passed, otherwise keep id order
+            let sort_by = if suffix.as_str() == "--due" { SortBy::Due } else { SortBy::Id };
+            let sort_label = match sort_by {
+                SortBy::Id => "sorted by id",
+                SortBy::Status => "sorted by status",
+                SortBy::Due => "sorted by due date",
+            };
             // retrieve a list of tasks from the database in the form of a Vec<Task>
-            let tasks = Task::list(&conn, false)?;
+            let tasks = Task::list(&conn, filter, sort_by)?;
             if tasks.is_empty() {
-                println!("No tasks found.");
+                println!("No {} found.", label);
             }
             else {
                 // print the list of tasks
-                println!("To-Do List (sorted by id):");
+                println!("To-Do List ({}, {}):", label, sort_label);
                 Task::print_list(tasks)?;
             }
             Ok(())
@@ -116,6 +139,67 @@ fn main() -> Result<()> {
         "sort" => {
             Ok(())
         }
+        "export" => {
+            // Default to a fixed filename in the current directory if none is given
+            let path = if suffix.as_str().is_empty() { "tasks_export.json" } else { suffix.as_str() };
+            Task::export(&conn, path)?;
+            Ok(())
+        }
+        "import" => {
+            let path = if suffix.as_str().is_empty() { "tasks_export.json" } else { suffix.as_str() };
+            Task::import(&conn, path)?;
+            Ok(())
+        }
+        "search" => {
+            let rest = &args[2..];
+            if rest.is_empty() {
+                help()?;
+                std::process::exit(1);
+            }
+            // Every argument other than a --regex flag (wherever it appears) makes up the search pattern
+            let use_regex = rest.iter().any(|arg| arg == "--regex");
+            let pattern = rest
+                .iter()
+                .filter(|arg| arg.as_str() != "--regex")
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" ");
+            if pattern.is_empty() {
+                help()?;
+                std::process::exit(1);
+            }
+            let tasks = Task::search(&conn, &pattern, use_regex)?;
+            if tasks.is_empty() {
+                println!("No tasks matching {:?} found.", pattern);
+            } else {
+                println!("To-Do List (matching {:?}):", pattern);
+                Task::print_list(tasks)?;
+            }
+            Ok(())
+        }
+        "backup" => {
+            // Default to a fixed filename in the current directory if none is given
+            let dest = if suffix.as_str().is_empty() { "tasks_backup.sqlite" } else { suffix.as_str() };
+            backup_db(&conn, dest)?;
+            Ok(())
+        }
+        "restore" => {
+            let src = if suffix.as_str().is_empty() { "tasks_backup.sqlite" } else { suffix.as_str() };
+            restore_db(&mut conn, src)?;
+            Ok(())
+        }
+        "serve" => {
+            // Accept an optional "--port N" pair; default to 8080 otherwise
+            let port: u16 = if args.len() >= 4 && args[2] == "--port" {
+                args[3].parse().unwrap_or(8080)
+            } else {
+                8080
+            };
+            // The CLI's main() is sync, so spin up a Tokio runtime just for this command
+            let rt = tokio::runtime::Runtime::new().expect("Failed to start the async runtime");
+            rt.block_on(server::serve(conn, port)).expect("Server error");
+            Ok(())
+        }
         "help" | "--help" | "-h" | _ => help(),
     }?;
 
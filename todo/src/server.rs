@@ -0,0 +1,72 @@
+// HTTP API mode: exposes the same task store the CLI uses over a small REST interface,
+// so other clients (a web UI, a mobile app, ...) can drive it without shelling out to `todo`.
+use axum::extract::{Path as AxumPath, State};
+use axum::http::StatusCode;
+use axum::routing::{get, patch};
+use axum::{Json, Router};
+use rusqlite::Connection;
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+
+use crate::{SortBy, Task, TaskFilter};
+
+// rusqlite::Connection is not Sync, so it can't be shared across async worker threads directly -
+// a Mutex-guarded handle is locked for the duration of each request instead
+type SharedConn = Arc<Mutex<Connection>>;
+
+// Request body for POST /tasks
+#[derive(Deserialize)]
+struct AddRequest {
+    name: String,
+}
+
+// Boots the HTTP API on the given port, serving the same task store the CLI uses
+pub async fn serve(conn: Connection, port: u16) -> std::io::Result<()> {
+    let state: SharedConn = Arc::new(Mutex::new(conn));
+
+    let app = Router::new()
+        .route("/tasks", get(list_tasks).post(add_task))
+        .route("/tasks/:id", patch(toggle_task).delete(remove_task))
+        .with_state(state);
+
+    let addr = format!("0.0.0.0:{}", port);
+    println!("Listening on http://{}", addr);
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    axum::serve(listener, app).await
+}
+
+// GET /tasks - list every task
+async fn list_tasks(State(state): State<SharedConn>) -> Result<Json<Vec<Task>>, StatusCode> {
+    let conn = state.lock().expect("Task DB mutex poisoned");
+    match Task::list(&conn, TaskFilter::All, SortBy::Id) {
+        Ok(tasks) => Ok(Json(tasks)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// POST /tasks - add a task
+async fn add_task(State(state): State<SharedConn>, Json(body): Json<AddRequest>) -> StatusCode {
+    let conn = state.lock().expect("Task DB mutex poisoned");
+    match Task::add(&conn, &body.name, None) {
+        Ok(()) => StatusCode::CREATED,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+// PATCH /tasks/{id} - toggle a task's done status
+async fn toggle_task(State(state): State<SharedConn>, AxumPath(id): AxumPath<i32>) -> StatusCode {
+    let conn = state.lock().expect("Task DB mutex poisoned");
+    match Task::toggle(&conn, id) {
+        Ok(()) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+// DELETE /tasks/{id} - remove a task
+async fn remove_task(State(state): State<SharedConn>, AxumPath(id): AxumPath<i32>) -> StatusCode {
+    let conn = state.lock().expect("Task DB mutex poisoned");
+    match Task::rm(&conn, id) {
+        Ok(()) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}